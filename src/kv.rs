@@ -3,42 +3,50 @@ use std::cmp::Ordering;
 use json::Json;
 
 #[derive(Debug,Clone)]
-pub struct KeyValue(String,Json);
+pub struct KeyValue(u64, String, Json);
 
 impl KeyValue {
     #[inline]
     pub fn new(key: String, value: Json) -> KeyValue {
-        KeyValue(key, value)
+        let hash = fnv1a_hash(&key);
+        KeyValue(hash, key, value)
     }
 
     #[inline]
     pub fn key(self) -> String {
-        self.0
+        self.1
     }
 
     #[inline]
     pub fn key_ref(&self) -> &String {
-        &self.0
+        &self.1
     }
 
     #[inline]
     pub fn value(self) -> Json {
-        self.1
+        self.2
     }
 
     #[inline]
     pub fn value_ref(&self) -> &Json {
-        &self.1
+        &self.2
     }
 
     #[inline]
     pub fn value_mut(&mut self) -> &mut Json {
-        &mut self.1
+        &mut self.2
     }
 
     #[inline]
     pub fn set_value(&mut self, value: Json) {
-        self.1 = value;
+        self.2 = value;
+    }
+
+    /// Pre-computed FNV-1a hash of the key, used to order the backing
+    /// `Vec<KeyValue>` and to short-circuit [search_by_key].
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.0
     }
 }
 
@@ -48,20 +56,38 @@ impl Eq for KeyValue {}
 
 impl PartialEq for KeyValue {
     fn eq(&self, other: &KeyValue) -> bool {
-        self.0 == other.0 // compare only the key.
+        // self.0 is a pure function of self.1, so it adds no
+        // discriminating power here; comparing it first is just a
+        // cheap u64 pre-filter before the authoritative &str compare.
+        self.0 == other.0 && self.1 == other.1
     }
 }
 
 impl PartialOrd for KeyValue {
     fn partial_cmp(&self, other: &KeyValue) -> Option<Ordering> {
-        self.0.partial_cmp(other.key_ref()) // compare only the key.
+        // compare by (hash, key), matching the order the backing
+        // Vec<KeyValue> is maintained in.
+        (self.0, &self.1).partial_cmp(&(other.0, &other.1))
     }
 }
 
+// Computes the 64-bit FNV-1a hash of `key`, keeping the backing
+// Vec<KeyValue> ordered by (hash, key) instead of key alone.
+#[inline]
+fn fnv1a_hash(key: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for b in key.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
 
 pub fn search_by_key(obj: &Vec<KeyValue>, key: &str) -> Result<usize,usize> {
     use std::cmp::Ordering::{Greater, Equal, Less};
 
+    let khash = fnv1a_hash(key);
+
     let mut size = obj.len();
     if size == 0 { return Err(0) }
 
@@ -72,20 +98,60 @@ pub fn search_by_key(obj: &Vec<KeyValue>, key: &str) -> Result<usize,usize> {
         // mid is always in [0, size), that means mid is >= 0 and < size.
         // mid >= 0: by definition
         // mid < size: mid = size / 2 + size / 4 + size / 8 ...
-        let item: &str = obj[mid].key_ref();
-        let cmp = item.cmp(key);
+        let cmp = cmp_hash_key(&obj[mid], khash, key);
         base = if cmp == Greater { base } else { mid };
         size -= half;
     }
     // base is always in [0, size) because base <= mid.
-    let item: &str = obj[base].key_ref();
-    let cmp = item.cmp(key);
+    let cmp = cmp_hash_key(&obj[base], khash, key);
     if cmp == Equal { Ok(base) } else { Err(base + (cmp == Less) as usize) }
 }
 
+// Compares `item` against `(khash, key)`, the hash first and the key
+// string only when hashes collide.
+#[inline]
+fn cmp_hash_key(item: &KeyValue, khash: u64, key: &str) -> Ordering {
+    match item.hash().cmp(&khash) {
+        Ordering::Equal => item.key_ref().as_str().cmp(key),
+        ord => ord,
+    }
+}
+
 pub fn upsert_object_key(obj: &mut Vec<KeyValue>, kv: KeyValue) {
     match search_by_key(obj, kv.key_ref()) {
         Ok(off) => obj[off] = kv,
         Err(off) => obj.insert(off, kv),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmp_hash_key_falls_back_to_key_compare_on_hash_collision() {
+        // Two keys sharing a hash: the u64 compare alone can't tell them
+        // apart, so the &str fallback has to decide.
+        let item = KeyValue(42, "a".to_string(), Json::Null);
+
+        assert_eq!(cmp_hash_key(&item, 42, "a"), Ordering::Equal);
+        assert_eq!(cmp_hash_key(&item, 42, "b"), Ordering::Less);
+        assert_eq!(cmp_hash_key(&item, 42, ""), Ordering::Greater);
+    }
+
+    #[test]
+    fn search_by_key_finds_present_and_absent_keys() {
+        let mut obj = vec![
+            KeyValue::new("a".to_string(), Json::Null),
+            KeyValue::new("b".to_string(), Json::Null),
+            KeyValue::new("c".to_string(), Json::Null),
+        ];
+        obj.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+        for key in ["a", "b", "c"] {
+            let idx = search_by_key(&obj, key).expect("key present");
+            assert_eq!(obj[idx].key_ref(), key);
+        }
+        assert!(search_by_key(&obj, "missing").is_err());
+    }
+}
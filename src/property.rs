@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::ops::Range;
 
 use json::Json;
 
@@ -8,14 +9,36 @@ use json::Json;
 /// * **key** is [String] type, defined by JSON spec.
 /// * **value** is JSON value.
 ///
-/// Implements [PartialEq] and [PartialOrd], where only the key component
-/// is considered for equality and ordering.
+/// Implements [PartialEq] and [PartialOrd], where only the key (and the
+/// key's hash) is considered for equality and ordering.
+///
+/// Objects are stored as a `Vec<Property>` kept sorted, not by key alone,
+/// but by `(hash, key)` where `hash` is a 64-bit FNV-1a fingerprint of the
+/// key computed once in [Property::new]. This turns the common-case probe
+/// in [search_by_key] into a single `u64` comparison, falling back to the
+/// `&str` compare only when two keys collide on their hash. Binary search
+/// over the sorted `Vec` is O(log n) regardless of key order, so this
+/// buys nothing for balance -- the only win is the cheaper per-probe
+/// comparison. Because the vector is no longer in lexical key order,
+/// callers that need that order (for example, deterministic
+/// serialization) should use [iter_by_key].
+///
+/// This `(hash, key)` order is a hard invariant: [search_by_key] assumes
+/// it and a `Vec<Property>` that violates it will make [upsert_object_key]
+/// insert a duplicate entry for a key that's already present instead of
+/// replacing it, silently corrupting the object. Any code that builds a
+/// `Vec<Property>` other than through [upsert_object_key] one entry at a
+/// time -- a parser materializing an object literal in one pass, for
+/// instance -- must route the result through [sort_for_object] first.
+/// The JSON parser and serializer are not part of this module and have
+/// not been audited against this invariant; do so before relying on it
+/// for objects that didn't pass through this module's own constructors.
 ///
 /// [string]: std::string::String
 /// [PartialEq]: std::cmp::PartialEq
 /// [PartialOrd]: std::cmp::PartialOrd
 #[derive(Debug,Clone)]
-pub struct Property(String,Json);
+pub struct Property(u64, String, Json);
 
 /// Following inherent methods are self explanatory, typically
 /// used to move, or obtain a reference for key or value
@@ -23,60 +46,214 @@ pub struct Property(String,Json);
 impl Property {
     #[inline]
     pub fn new<T>(key: T, value: Json) -> Property where T: ToString {
-        Property(key.to_string(), value)
+        let key = key.to_string();
+        let hash = fnv1a_hash(&key);
+        Property(hash, key, value)
     }
 
     #[inline]
     pub fn key(self) -> String {
-        self.0
+        self.1
     }
 
     #[inline]
     pub fn key_ref(&self) -> &String {
-        &self.0
+        &self.1
     }
 
     #[inline]
     pub fn value(self) -> Json {
-        self.1
+        self.2
     }
 
     #[inline]
     pub fn value_ref(&self) -> &Json {
-        &self.1
+        &self.2
     }
 
     #[inline]
     pub fn value_mut(&mut self) -> &mut Json {
-        &mut self.1
+        &mut self.2
     }
 
     #[inline]
     pub fn set_value(&mut self, value: Json) {
-        self.1 = value;
+        self.2 = value;
+    }
+
+    /// Pre-computed FNV-1a hash of the key, used to order the backing
+    /// `Vec<Property>` and to short-circuit [search_by_key].
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.0
+    }
+
+    /// Orders two properties by key and then, on equal keys, by value,
+    /// giving a deterministic total order that [PartialOrd] can't
+    /// provide since it only ever compares keys. Values are ordered by
+    /// type first -- null < bool < number < string < array < object --
+    /// then numerically, lexically, or length-then-entrywise as
+    /// appropriate; see [cmp_value].
+    pub fn cmp_full(&self, other: &Property) -> Ordering {
+        match self.key_ref().cmp(other.key_ref()) {
+            Ordering::Equal => cmp_value(self.value_ref(), other.value_ref()),
+            ord => ord,
+        }
+    }
+}
+
+/// Type-then-value ordering for a pair of JSON values: null < bool <
+/// number < string < array < object. Numbers compare numerically
+/// (mixing integer and float as needed), strings lexically, and
+/// arrays/objects compare length-first, then element-wise -- the same
+/// length-then-entrywise dictionary comparison used by other JSON value
+/// libraries.
+pub fn cmp_value(a: &Json, b: &Json) -> Ordering {
+    match (a, b) {
+        (Json::Null, Json::Null) => Ordering::Equal,
+        (Json::Bool(a), Json::Bool(b)) => a.cmp(b),
+        (Json::Integer(a), Json::Integer(b)) => a.cmp(b),
+        // NaN gets a fixed rank above every other number, sign ignored,
+        // instead of comparing Equal to everything (total_cmp would also
+        // give it a fixed rank, but a sign-aware one -- a negative-signed
+        // NaN would sort below -inf -- which disagrees with cmp_int_float
+        // below and breaks the transitivity sort and dedup rely on).
+        // partial_cmp handles -0.0 == 0.0 the way cmp_int_float already
+        // does, so the two stay consistent with each other too.
+        (Json::Float(a), Json::Float(b)) => match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a.partial_cmp(b).unwrap(),
+        },
+        (Json::Integer(a), Json::Float(b)) => cmp_int_float(*a, *b),
+        (Json::Float(a), Json::Integer(b)) => cmp_int_float(*b, *a).reverse(),
+        (Json::String(a), Json::String(b)) => a.cmp(b),
+        (Json::Array(a), Json::Array(b)) => cmp_by_len_then_elems(a, b, cmp_value),
+        (Json::Object(a), Json::Object(b)) => {
+            cmp_by_len_then_elems(a, b, Property::cmp_full)
+        }
+        (a, b) => json_type_rank(a).cmp(&json_type_rank(b)),
+    }
+}
+
+// Compares an `i128` against an `f64` without going through a lossy
+// `as f64` cast, which rounds integers beyond the 53-bit mantissa to the
+// nearest representable float and can make two numerically distinct
+// values compare Equal. `f.floor()` is always exactly representable (it
+// has no fractional part regardless of magnitude), so it can be cast
+// back to `i128` losslessly as long as it's in range; any leftover
+// fractional part on `f` then only decides ties.
+fn cmp_int_float(i: i128, f: f64) -> Ordering {
+    if f.is_nan() {
+        // NaN sorts above every other float (see cmp_value), so it also
+        // sorts above every integer.
+        return Ordering::Less;
+    }
+    if f.is_infinite() {
+        return if f.is_sign_positive() {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+    }
+
+    const I128_BOUND: f64 = 170_141_183_460_469_231_731_687_303_715_884_105_728.0; // 2^127
+
+    let f_floor = f.floor();
+    if f_floor >= I128_BOUND {
+        return Ordering::Less;
+    }
+    if f_floor < -I128_BOUND {
+        return Ordering::Greater;
+    }
+
+    let floor_i = f_floor as i128;
+    match i.cmp(&floor_i) {
+        Ordering::Equal if f > f_floor => Ordering::Less,
+        ord => ord,
+    }
+}
+
+// Compares two same-length-comparable sequences by length first, then
+// element-wise using `cmp_elem`, the first non-equal element deciding
+// the outcome.
+fn cmp_by_len_then_elems<T>(
+    a: &[T],
+    b: &[T],
+    cmp_elem: impl Fn(&T, &T) -> Ordering,
+) -> Ordering {
+    match a.len().cmp(&b.len()) {
+        Ordering::Equal => a
+            .iter()
+            .zip(b.iter())
+            .map(|(x, y)| cmp_elem(x, y))
+            .find(|ord| *ord != Ordering::Equal)
+            .unwrap_or(Ordering::Equal),
+        ord => ord,
     }
 }
 
+// Relative rank of a value's JSON type: null < bool < number < string <
+// array < object.
+fn json_type_rank(value: &Json) -> u8 {
+    match value {
+        Json::Null => 0,
+        Json::Bool(_) => 1,
+        Json::Integer(_) | Json::Float(_) => 2,
+        Json::String(_) => 3,
+        Json::Array(_) => 4,
+        Json::Object(_) => 5,
+    }
+}
+
+/// Orders two objects by length first, then entry-wise using
+/// [Property::cmp_full], giving a deterministic content ordering for
+/// whole documents that doesn't collide on keys the way [PartialOrd]
+/// does.
+pub fn cmp_object_full(a: &[Property], b: &[Property]) -> Ordering {
+    cmp_by_len_then_elems(a, b, Property::cmp_full)
+}
+
 // Eq, PartialEq and PartialOrd
 
 impl Eq for Property {}
 
 impl PartialEq for Property {
     fn eq(&self, other: &Property) -> bool {
-        self.0 == other.0 // compare only the key.
+        // self.0 is a pure function of self.1, so it adds no
+        // discriminating power here; comparing it first is just a
+        // cheap u64 pre-filter before the authoritative &str compare.
+        self.0 == other.0 && self.1 == other.1
     }
 }
 
 impl PartialOrd for Property {
     fn partial_cmp(&self, other: &Property) -> Option<Ordering> {
-        self.0.partial_cmp(other.key_ref()) // compare only the key.
+        // compare by (hash, key), matching the order the backing
+        // Vec<Property> is maintained in.
+        (self.0, &self.1).partial_cmp(&(other.0, &other.1))
     }
 }
 
+/// Computes the 64-bit FNV-1a hash of `key`, borrowed from json-rust's
+/// object store. Used to keep the backing `Vec<Property>` ordered by
+/// `(hash, key)` instead of `key` alone.
+#[inline]
+fn fnv1a_hash(key: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for b in key.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
 
 pub fn search_by_key(obj: &Vec<Property>, key: &str) -> Result<usize,usize> {
     use std::cmp::Ordering::{Greater, Equal, Less};
 
+    let khash = fnv1a_hash(key);
+
     let mut size = obj.len();
     if size == 0 { return Err(0) }
 
@@ -87,20 +264,305 @@ pub fn search_by_key(obj: &Vec<Property>, key: &str) -> Result<usize,usize> {
         // mid is always in [0, size), that means mid is >= 0 and < size.
         // mid >= 0: by definition
         // mid < size: mid = size / 2 + size / 4 + size / 8 ...
-        let item: &str = obj[mid].key_ref();
-        let cmp = item.cmp(key);
+        let cmp = cmp_hash_key(&obj[mid], khash, key);
         base = if cmp == Greater { base } else { mid };
         size -= half;
     }
     // base is always in [0, size) because base <= mid.
-    let item: &str = obj[base].key_ref();
-    let cmp = item.cmp(key);
+    let cmp = cmp_hash_key(&obj[base], khash, key);
     if cmp == Equal { Ok(base) } else { Err(base + (cmp == Less) as usize) }
 }
 
+// Compares `item` against `(khash, key)`, the hash first and the key
+// string only when hashes collide.
+#[inline]
+fn cmp_hash_key(item: &Property, khash: u64, key: &str) -> Ordering {
+    match item.hash().cmp(&khash) {
+        Ordering::Equal => item.key_ref().as_str().cmp(key),
+        ord => ord,
+    }
+}
+
 pub fn upsert_object_key(obj: &mut Vec<Property>, prop: Property) {
     match search_by_key(obj, prop.key_ref()) {
         Ok(off) => obj[off] = prop,
         Err(off) => obj.insert(off, prop),
     }
-}
\ No newline at end of file
+}
+
+/// Sorts `obj` into the `(hash, key)` order [search_by_key] and
+/// [upsert_object_key] require. If `obj` has more than one entry for the
+/// same key, the one appearing earliest in the input is kept (the sort
+/// is stable) and the rest are dropped. Any caller that assembles a
+/// `Vec<Property>` some way other than repeated [upsert_object_key]
+/// calls -- for example a parser building an object literal's
+/// properties in one pass -- must run the result through this before
+/// treating it as a well-formed object.
+pub fn sort_for_object(obj: &mut Vec<Property>) {
+    obj.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    obj.dedup_by(|a, b| a.key_ref() == b.key_ref());
+}
+
+/// Returns an iterator over `obj` in lexical key order.
+///
+/// The backing vector is maintained in `(hash, key)` order for fast
+/// lookup, so callers that relied on the old key-sorted iteration order
+/// (for instance, deterministic serialization) should use this instead
+/// of iterating `obj` directly.
+pub fn iter_by_key(obj: &[Property]) -> impl Iterator<Item = &Property> {
+    let mut refs: Vec<&Property> = obj.iter().collect();
+    refs.sort_by(|a, b| a.key_ref().cmp(b.key_ref()));
+    refs.into_iter()
+}
+
+/// Returns the half-open index range, within `sorted`, of every entry
+/// whose key starts with `prefix`.
+///
+/// `sorted` must already be in lexical key order, see [iter_by_key],
+/// because the backing object vector is itself kept in `(hash, key)`
+/// order for fast lookup by exact key and so isn't prefix-searchable on
+/// its own. Binary-searches for the lower bound (the first key `>=
+/// prefix`) and the upper bound (the first key not sharing `prefix`,
+/// found by bumping the prefix's last byte), the same lower-bound/
+/// upper-bound bracketing used to locate a key region in extent-
+/// addressed LSM record stores. An empty range means no match.
+pub fn search_by_prefix(sorted: &[&Property], prefix: &str) -> Range<usize> {
+    let prefix = prefix.as_bytes();
+    if prefix.is_empty() {
+        return 0..sorted.len();
+    }
+    let lo = sorted.partition_point(|p| p.key_ref().as_bytes() < prefix);
+    let hi = match prefix_upper_bound(prefix) {
+        Some(upper) => sorted.partition_point(|p| p.key_ref().as_bytes() < upper.as_slice()),
+        None => sorted.len(),
+    };
+    lo..hi
+}
+
+// Smallest byte-string that is strictly greater than every string
+// sharing `prefix`, obtained by incrementing prefix's last byte. Returns
+// None if the prefix is all 0xff bytes, meaning no finite upper bound
+// exists and the matching range extends to the end of `sorted`.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = prefix.to_vec();
+    while let Some(&last) = bytes.last() {
+        if last == 0xff {
+            bytes.pop();
+        } else {
+            *bytes.last_mut().unwrap() += 1;
+            return Some(bytes);
+        }
+    }
+    None
+}
+
+/// Merges `incoming` into `base` in a single O(n+m) pass, assuming both
+/// are already in the object's canonical `(hash, key)` order (as
+/// maintained by [upsert_object_key]). Two cursors walk both vectors in
+/// lock-step; when a key is present on both sides, `on_conflict` is
+/// called with the surviving entry from `base` and the incoming entry so
+/// the caller can replace, keep, or deep-merge nested values. This is
+/// the JSON analogue of the scope-inheritance merge used to combine an
+/// interpreter's variable bindings (overriding or skipping on
+/// collision), and it makes `Json + Json`-style composition and
+/// config-overlay use cases efficient without the O(n*m) cost of
+/// inserting one property at a time.
+pub fn merge_objects<F>(base: &mut Vec<Property>, incoming: Vec<Property>, mut on_conflict: F)
+where
+    F: FnMut(&mut Property, Property),
+{
+    let mut merged = Vec::with_capacity(base.len() + incoming.len());
+    let mut bi = std::mem::take(base).into_iter().peekable();
+    let mut ii = incoming.into_iter().peekable();
+
+    loop {
+        match (bi.peek(), ii.peek()) {
+            (Some(b), Some(i)) => match b.partial_cmp(i).unwrap() {
+                Ordering::Less => merged.push(bi.next().unwrap()),
+                Ordering::Greater => merged.push(ii.next().unwrap()),
+                Ordering::Equal => {
+                    let mut b = bi.next().unwrap();
+                    let i = ii.next().unwrap();
+                    on_conflict(&mut b, i);
+                    merged.push(b);
+                }
+            },
+            (Some(_), None) => merged.push(bi.next().unwrap()),
+            (None, Some(_)) => merged.push(ii.next().unwrap()),
+            (None, None) => break,
+        }
+    }
+
+    *base = merged;
+}
+
+impl Json {
+    /// Returns every property of this object whose key starts with
+    /// `prefix`, e.g. `json.select_prefix("user.")` to pull a flattened
+    /// sub-tree. Returns an empty vector if `self` isn't [Json::Object].
+    ///
+    /// This is a one-shot convenience, not the `O(log n)` shortcut
+    /// [search_by_prefix] offers: the backing vector is kept in `(hash,
+    /// key)` order rather than key order, so every call first pays
+    /// `O(n log n)` to build the key-sorted view via [iter_by_key]
+    /// before the `O(log n)` range search runs on it. A caller pulling
+    /// more than one prefix out of the same object should build that
+    /// sorted view once with `iter_by_key(obj).collect()` and call
+    /// [search_by_prefix] directly against it instead of calling this
+    /// method repeatedly.
+    pub fn select_prefix(&self, prefix: &str) -> Vec<&Property> {
+        match self {
+            Json::Object(obj) => {
+                let sorted: Vec<&Property> = iter_by_key(obj).collect();
+                let range = search_by_prefix(&sorted, prefix);
+                sorted[range].to_vec()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmp_hash_key_falls_back_to_key_compare_on_hash_collision() {
+        // Two keys sharing a hash: the u64 compare alone can't tell them
+        // apart, so the &str fallback has to decide.
+        let item = Property(42, "a".to_string(), Json::Null);
+
+        assert_eq!(cmp_hash_key(&item, 42, "a"), Ordering::Equal);
+        assert_eq!(cmp_hash_key(&item, 42, "b"), Ordering::Less);
+        assert_eq!(cmp_hash_key(&item, 42, ""), Ordering::Greater);
+    }
+
+    #[test]
+    fn search_by_key_finds_present_and_absent_keys() {
+        let mut obj = vec![
+            Property::new("a", Json::Null),
+            Property::new("b", Json::Null),
+            Property::new("c", Json::Null),
+        ];
+        obj.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+        for key in ["a", "b", "c"] {
+            let idx = search_by_key(&obj, key).expect("key present");
+            assert_eq!(obj[idx].key_ref(), key);
+        }
+        assert!(search_by_key(&obj, "missing").is_err());
+    }
+
+    #[test]
+    fn search_by_prefix_empty_prefix_matches_everything() {
+        let a = Property::new("alpha", Json::Null);
+        let b = Property::new("beta", Json::Null);
+        let sorted = vec![&a, &b];
+
+        assert_eq!(search_by_prefix(&sorted, ""), 0..2);
+    }
+
+    #[test]
+    fn search_by_prefix_includes_key_equal_to_prefix() {
+        let beta = Property::new("beta", Json::Null);
+        let beta2 = Property::new("beta2", Json::Null);
+        let gamma = Property::new("gamma", Json::Null);
+        let sorted = vec![&beta, &beta2, &gamma];
+
+        assert_eq!(search_by_prefix(&sorted, "beta"), 0..2);
+    }
+
+    #[test]
+    fn search_by_prefix_no_match_returns_empty_range() {
+        let alpha = Property::new("alpha", Json::Null);
+        let gamma = Property::new("gamma", Json::Null);
+        let sorted = vec![&alpha, &gamma];
+
+        let range = search_by_prefix(&sorted, "beta");
+        assert_eq!(range.start, range.end);
+    }
+
+    #[test]
+    fn prefix_upper_bound_has_no_finite_bound_past_all_0xff() {
+        assert_eq!(prefix_upper_bound(&[0xff, 0xff]), None);
+        assert_eq!(prefix_upper_bound(b"ab"), Some(b"ac".to_vec()));
+    }
+
+    #[test]
+    fn merge_objects_lets_on_conflict_choose_the_winner() {
+        let mut base = vec![
+            Property::new("a", Json::Integer(1)),
+            Property::new("b", Json::Integer(2)),
+        ];
+        base.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        let mut incoming = vec![
+            Property::new("b", Json::Integer(20)),
+            Property::new("c", Json::Integer(3)),
+        ];
+        incoming.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+        merge_objects(&mut base, incoming, |kept, incoming| {
+            *kept.value_mut() = incoming.value();
+        });
+
+        let sorted: Vec<&Property> = iter_by_key(&base).collect();
+        assert_eq!(sorted.len(), 3);
+        assert_eq!(sorted[0].key_ref(), "a");
+        assert_eq!(sorted[1].key_ref(), "b");
+        assert!(matches!(sorted[1].value_ref(), Json::Integer(20)));
+        assert_eq!(sorted[2].key_ref(), "c");
+    }
+
+    #[test]
+    fn cmp_value_orders_by_type_then_by_value() {
+        assert_eq!(cmp_value(&Json::Null, &Json::Bool(false)), Ordering::Less);
+        assert_eq!(cmp_value(&Json::Integer(5), &Json::Float(5.5)), Ordering::Less);
+        assert_eq!(cmp_value(&Json::Integer(6), &Json::Float(5.5)), Ordering::Greater);
+        assert_eq!(
+            cmp_value(&Json::String("a".to_string()), &Json::Array(vec![])),
+            Ordering::Less,
+        );
+    }
+
+    #[test]
+    fn cmp_value_compares_big_integers_against_floats_without_precision_loss() {
+        // Both truncate to the same f64 under a naive `as f64` cast, but
+        // they are not numerically equal.
+        assert_eq!(
+            cmp_value(
+                &Json::Integer(9_007_199_254_740_993),
+                &Json::Float(9_007_199_254_740_992.0),
+            ),
+            Ordering::Greater,
+        );
+        assert_eq!(
+            cmp_value(
+                &Json::Float(9_007_199_254_740_992.0),
+                &Json::Integer(9_007_199_254_740_993),
+            ),
+            Ordering::Less,
+        );
+        assert_eq!(cmp_value(&Json::Integer(5), &Json::Float(f64::NAN)), Ordering::Less);
+        assert_eq!(
+            cmp_value(&Json::Integer(5), &Json::Float(f64::INFINITY)),
+            Ordering::Less,
+        );
+    }
+
+    #[test]
+    fn cmp_value_gives_nan_a_fixed_deterministic_position() {
+        assert_eq!(cmp_value(&Json::Float(f64::NAN), &Json::Float(1.0)), Ordering::Greater);
+        assert_eq!(cmp_value(&Json::Float(1.0), &Json::Float(f64::NAN)), Ordering::Less);
+        assert_eq!(cmp_value(&Json::Float(f64::NAN), &Json::Float(f64::NAN)), Ordering::Equal);
+        // NaN's rank ignores sign, unlike total_cmp, so it stays
+        // consistent with cmp_int_float's sign-independent NaN handling.
+        assert_eq!(cmp_value(&Json::Float(-f64::NAN), &Json::Float(f64::NEG_INFINITY)), Ordering::Greater);
+    }
+
+    #[test]
+    fn cmp_value_agrees_on_negative_zero_across_integer_and_float() {
+        assert_eq!(cmp_value(&Json::Float(-0.0), &Json::Float(0.0)), Ordering::Equal);
+        assert_eq!(cmp_value(&Json::Float(-0.0), &Json::Integer(0)), Ordering::Equal);
+        assert_eq!(cmp_value(&Json::Integer(0), &Json::Float(0.0)), Ordering::Equal);
+    }
+}